@@ -0,0 +1,98 @@
+//! The `Backend` trait lets a single `IrModule` be rendered to more than
+//! one schema language. `emit::emit_module` walks the IR and calls these
+//! methods; each backend only has to know how to render its own syntax.
+
+/// Where a field is being emitted: inside an ordinary message/record body,
+/// or inside a data-carrying enum's case list (a proto `oneof`, a WIT
+/// `variant`). Most backends render both the same way, but some (WIT)
+/// use different syntax for the two.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldSite {
+    Message,
+    Case,
+}
+
+pub(crate) trait Backend {
+    /// Called once before any other method, with this module's own name
+    /// and the names of the other bundled modules it references. Backends
+    /// with no file-level header (WIT) can leave the default no-op.
+    fn begin_file(&mut self, module_name: &str, imports: &[String]) {
+        let _ = (module_name, imports);
+    }
+
+    /// Renders a reference to `name`, defined in the other bundled module
+    /// `module_name`, as this backend's syntax for a qualified/imported
+    /// type name. Defaults to the bare name for backends that don't
+    /// distinguish local from imported types.
+    fn qualify_foreign(&self, module_name: &str, name: &str) -> String {
+        let _ = module_name;
+        name.to_string()
+    }
+
+    fn begin_message(&mut self, name: &str);
+
+    /// Begins the block holding a data-carrying enum's cases: a proto
+    /// `oneof`, or a WIT `variant`.
+    fn begin_variant(&mut self, name: &str);
+
+    /// Renders a type's `#[proto(reserved = "...")]` ranges, if any, right
+    /// after `begin_message`/`begin_enum`. Defaults to a no-op for backends
+    /// (WIT) with no concept of reserved field numbers.
+    fn reserved(&mut self, ranges: &[(u32, u32)]) {
+        let _ = ranges;
+    }
+
+    fn end(&mut self);
+
+    /// Whether a data-carrying enum's `begin_variant` block must be nested
+    /// inside a `begin_message` wrapper (proto's `oneof`-in-`message`
+    /// idiom), or stands on its own (WIT's top-level `variant`).
+    fn variant_needs_message_wrapper(&self) -> bool {
+        true
+    }
+
+    /// Translates a Rust scalar type name (`u32`, `String`, ...) to this
+    /// backend's primitive spelling, or `None` if `name` isn't a scalar
+    /// (and should be treated as a reference to another message).
+    fn scalar_type_name(&self, name: &str) -> Option<&'static str>;
+
+    fn scalar_field(&mut self, site: FieldSite, name: &str, ty: &str, id: &mut usize);
+    fn message_field(&mut self, site: FieldSite, name: &str, ty: &str, id: &mut usize);
+    fn repeated_field(&mut self, site: FieldSite, name: &str, ty: &str, id: &mut usize);
+    fn map_field(&mut self, site: FieldSite, name: &str, key_ty: &str, value_ty: &str, id: &mut usize);
+    fn optional_field(&mut self, site: FieldSite, name: &str, ty: &str, id: &mut usize);
+
+    /// `Vec<u8>`. Defaults to an ordinary repeated `u8` field; backends
+    /// with a native byte-string type (proto's `bytes`) override this.
+    fn bytes_field(&mut self, site: FieldSite, name: &str, id: &mut usize) {
+        self.repeated_field(site, name, "u8", id);
+    }
+
+    /// Whether fieldless enums can be rendered as this backend's native
+    /// enum construct instead of the `begin_variant` wrapper fallback.
+    fn supports_native_enum(&self) -> bool {
+        false
+    }
+
+    fn begin_enum(&mut self, name: &str) {
+        let _ = name;
+        // Only called when `supports_native_enum` is true, which backends
+        // without a native enum construct never set.
+        unreachable!("backend does not support native enums");
+    }
+
+    fn enum_variant(&mut self, name: &str, discriminant: usize) {
+        let _ = (name, discriminant);
+        // Only called when `supports_native_enum` is true, which backends
+        // without a native enum construct never set.
+        unreachable!("backend does not support native enums");
+    }
+
+    /// Whether this backend's native enum requires a variant mapping to
+    /// discriminant 0 (proto3's rule for its `enum` construct). When true
+    /// and no variant claims 0, `emit_enum` synthesizes a
+    /// `{NAME}_UNSPECIFIED = 0` variant ahead of the real ones.
+    fn native_enum_requires_zero_variant(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,127 @@
+//! Groups several `IrModule`s loaded from different input files into one
+//! `Bundle`, with a shared symbol table mapping each type name to the
+//! module that defines it. This mirrors the `BundleContext`/`ModuleContext`
+//! split used by schema compilers that need to resolve type references
+//! across files (e.g. the preserves schema compiler): a `Bundle` owns every
+//! module, while a `ModuleContext` pairs the bundle with whichever module
+//! is currently being emitted, so `emit::emit_module` can tell whether a
+//! referenced type is local, defined in another bundled module, or
+//! genuinely undefined.
+
+use std::collections::HashMap;
+
+use crate::ir::{Fields, IrModule, Ty, TypeRef};
+
+/// The outcome of looking up a type name against a `Bundle`'s symbol table
+/// from the point of view of one of its modules.
+pub(crate) enum Resolution {
+    /// Defined in the module doing the looking-up.
+    Local,
+    /// Defined in a different bundled module, named here.
+    Foreign(String),
+    /// Not defined in any bundled module.
+    Undefined,
+}
+
+pub(crate) struct Bundle {
+    pub(crate) modules: Vec<(String, IrModule)>,
+    symbols: HashMap<String, String>,
+}
+
+impl Bundle {
+    /// Builds the symbol table eagerly so lookups (and duplicate-definition
+    /// errors) don't depend on the order modules are visited later.
+    pub(crate) fn new(modules: Vec<(String, IrModule)>) -> Bundle {
+        let mut symbols = HashMap::new();
+        for (module_name, ir) in &modules {
+            for name in &ir.types_in_order {
+                if let Some(prev) = symbols.insert(name.clone(), module_name.clone()) {
+                    panic!("type `{}` is defined in both `{}` and `{}`", name, prev, module_name);
+                }
+            }
+        }
+        Bundle { modules, symbols }
+    }
+
+    pub(crate) fn module(&self, name: &str) -> &IrModule {
+        &self.modules.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    pub(crate) fn resolve(&self, current_module: &str, name: &str) -> Resolution {
+        match self.symbols.get(name) {
+            Some(owner) if owner == current_module => Resolution::Local,
+            Some(owner) => Resolution::Foreign(owner.clone()),
+            None => Resolution::Undefined,
+        }
+    }
+}
+
+/// Pairs a `Bundle` with the module currently being emitted.
+pub(crate) struct ModuleContext<'a> {
+    pub(crate) bundle: &'a Bundle,
+    pub(crate) module_name: &'a str,
+}
+
+impl<'a> ModuleContext<'a> {
+    pub(crate) fn ir(&self) -> &'a IrModule {
+        self.bundle.module(self.module_name)
+    }
+
+    /// Every other bundled module referenced, directly or through
+    /// `Vec`/`Option`/`HashMap`, by one of this module's own types.
+    /// `is_scalar` lets the caller's backend decide which names are
+    /// primitives rather than type references.
+    pub(crate) fn foreign_modules_referenced(&self, is_scalar: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut names = Vec::new();
+        for type_name in &self.ir().types_in_order {
+            match &self.ir().types[type_name] {
+                Ty::Struct(s) => collect_referenced_names(&s.fields, &mut names),
+                Ty::Enum(item) => {
+                    for (_, fields, _, _) in &item.variants {
+                        collect_referenced_names(fields, &mut names);
+                    }
+                }
+            }
+        }
+
+        let mut foreign = Vec::new();
+        for name in names {
+            if is_scalar(&name) {
+                continue;
+            }
+            if let Resolution::Foreign(module) = self.bundle.resolve(self.module_name, &name) {
+                if !foreign.contains(&module) {
+                    foreign.push(module);
+                }
+            }
+        }
+        foreign.sort();
+        foreign
+    }
+}
+
+fn collect_referenced_names(fields: &Fields, out: &mut Vec<String>) {
+    match fields {
+        Fields::Tuple(fields) => {
+            for (ty, _) in fields {
+                collect_referenced_names_ref(ty, out);
+            }
+        }
+        Fields::Struct(fields) => {
+            for (_, ty, _) in fields {
+                collect_referenced_names_ref(ty, out);
+            }
+        }
+    }
+}
+
+fn collect_referenced_names_ref(ty: &TypeRef, out: &mut Vec<String>) {
+    match ty {
+        TypeRef::Normal(name) => out.push(name.clone()),
+        TypeRef::Generic(_, args) => {
+            for arg in args {
+                collect_referenced_names_ref(arg, out);
+            }
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Walks an `IrModule` and drives a `Backend` to render it. Shared by
+//! every concrete backend (`proto_emit`, `wit_emit`, ...).
+
+use crate::backend::{Backend, FieldSite};
+use crate::bundle::{ModuleContext, Resolution};
+use crate::ir::{to_underscore_case, EnumTy, Fields, StructTy, Ty, TypeRef};
+
+pub(crate) fn emit_module<B: Backend>(ctx: &ModuleContext, e: &mut B) {
+    let imports = ctx.foreign_modules_referenced(|name| e.scalar_type_name(name).is_some());
+    e.begin_file(ctx.module_name, &imports);
+
+    for name in &ctx.ir().types_in_order {
+        let ty = &ctx.ir().types[name];
+
+        match ty {
+            Ty::Struct(item) => emit_struct(ctx, name, item, e),
+            Ty::Enum(item) => emit_enum(ctx, name, item, e),
+        }
+    }
+}
+
+/// Computes the final field/case number for each element of a fields (or
+/// variants) list, honoring any explicit `#[proto(id = N)]` pins: pinned
+/// numbers are kept as-is (panicking on a duplicate pin), and every
+/// unpinned element is auto-filled with the next number not already taken
+/// or `reserved`, starting from 1.
+pub(crate) fn assign_numbers(explicit: &[Option<u32>], reserved: &[(u32, u32)]) -> Vec<u32> {
+    let mut used = std::collections::HashSet::new();
+    for id in explicit.iter().flatten() {
+        if !used.insert(*id) {
+            panic!("field number {} is used more than once", id);
+        }
+    }
+    let is_reserved = |n: u32| reserved.iter().any(|(start, end)| n >= *start && n <= *end);
+
+    let mut next = 1u32;
+    explicit.iter().map(|id| match id {
+        Some(id) => *id,
+        None => {
+            while used.contains(&next) || is_reserved(next) {
+                next += 1;
+            }
+            used.insert(next);
+            next
+        }
+    }).collect()
+}
+
+fn emit_enum<B: Backend>(ctx: &ModuleContext, name: &str, item: &EnumTy, e: &mut B) {
+    if e.supports_native_enum() && item.variants.iter().all(|(_, fields, _, _)| is_fieldless(fields)) {
+        e.begin_enum(name);
+        e.reserved(&item.reserved);
+
+        let mut next = 0i64;
+        let discriminants: Vec<(&str, i64)> = item.variants.iter().map(|(variant_name, _, discriminant, _)| {
+            let value = discriminant.unwrap_or(next);
+            next = value + 1;
+            (variant_name.as_str(), value)
+        }).collect();
+
+        if e.native_enum_requires_zero_variant() && !discriminants.iter().any(|(_, v)| *v == 0) {
+            e.enum_variant(&format!("{}_UNSPECIFIED", name.to_uppercase()), 0);
+        }
+        for (variant_name, value) in discriminants {
+            e.enum_variant(variant_name, value as usize);
+        }
+
+        e.end();
+        return;
+    }
+
+    let wrapped = e.variant_needs_message_wrapper();
+    if wrapped {
+        e.begin_message(name);
+        e.reserved(&item.reserved);
+    }
+    e.begin_variant(&to_underscore_case(name));
+
+    let numbers = assign_numbers(&item.variants.iter().map(|(_, _, _, id)| *id).collect::<Vec<_>>(), &item.reserved);
+
+    let mut to_append = Vec::new();
+    for ((field_name, fields, _, _), number) in item.variants.iter().zip(&numbers) {
+        let mut id = *number as usize;
+        if let Some(ty) = fields.singleton() {
+            emit_field(ctx, FieldSite::Case, field_name, ty, &mut id, e);
+        } else {
+            to_append.push((field_name, fields));
+
+            e.scalar_field(FieldSite::Case, field_name, &format!("{}{}", name, field_name), &mut id);
+        }
+    }
+
+    e.end();
+    if wrapped {
+        e.end();
+    }
+
+    for (field_name, fields) in to_append {
+        emit_fields(ctx, &format!("{}{}", name, field_name), fields, &[], e);
+    }
+}
+
+fn is_fieldless(fields: &Fields) -> bool {
+    match fields {
+        Fields::Tuple(f) => f.is_empty(),
+        Fields::Struct(f) => f.is_empty(),
+    }
+}
+
+fn emit_fields<B: Backend>(ctx: &ModuleContext, name: &str, fields: &Fields, reserved: &[(u32, u32)], e: &mut B) {
+    e.begin_message(name);
+    e.reserved(reserved);
+    match fields {
+        Fields::Tuple(_) => panic!(),
+        Fields::Struct(fields) => {
+            let numbers = assign_numbers(&fields.iter().map(|(_, _, id)| *id).collect::<Vec<_>>(), reserved);
+            for ((field_name, ty, _), number) in fields.iter().zip(&numbers) {
+                let mut id = *number as usize;
+                emit_field(ctx, FieldSite::Message, field_name, ty, &mut id, e);
+            }
+        }
+    }
+    e.end();
+}
+
+/// Resolves a non-scalar type name referenced from `ctx`'s module against
+/// the bundle: local names pass through unchanged, names defined in
+/// another bundled module are qualified via `Backend::qualify_foreign`,
+/// and anything not defined anywhere is a hard error rather than a
+/// dangling identifier in the emitted schema.
+fn resolve_type_name<B: Backend>(ctx: &ModuleContext, e: &B, name: &str) -> String {
+    match ctx.bundle.resolve(ctx.module_name, name) {
+        Resolution::Local => name.to_string(),
+        Resolution::Foreign(module) => e.qualify_foreign(&module, name),
+        Resolution::Undefined => {
+            panic!("type `{}`, referenced from module `{}`, is not defined in any bundled file", name, ctx.module_name)
+        }
+    }
+}
+
+fn emit_field<B: Backend>(ctx: &ModuleContext, site: FieldSite, field_name: &str, ty: &TypeRef, id: &mut usize, e: &mut B) {
+    match ty {
+        TypeRef::Normal(ty) => {
+            if let Some(simple) = e.scalar_type_name(ty) {
+                e.scalar_field(site, field_name, simple, id);
+            } else {
+                let qualified = resolve_type_name(ctx, e, ty);
+                if is_native_enum(ctx, e, ty) {
+                    // A fieldless enum rendered as a native `enum`: treat it
+                    // like a scalar field type rather than wrapping it in a
+                    // nested message (it isn't one, so `message_field`'s
+                    // nullable annotation wouldn't apply).
+                    e.scalar_field(site, field_name, &qualified, id);
+                } else {
+                    e.message_field(site, field_name, &qualified, id);
+                }
+            };
+        }
+        TypeRef::Generic(ty, args) => {
+            match ty.as_str() {
+                "Vec" => {
+                    let single = singular(args).unwrap();
+                    match single {
+                        TypeRef::Normal(name) => {
+                            if name == "u8" {
+                                e.bytes_field(site, field_name, id);
+                            } else if let Some(simple) = e.scalar_type_name(name) {
+                                e.repeated_field(site, field_name, simple, id);
+                            } else {
+                                let qualified = resolve_type_name(ctx, e, name);
+                                e.repeated_field(site, field_name, &qualified, id);
+                            }
+                        }
+                        TypeRef::Generic(_, _) => panic!(),
+                    }
+                }
+                "HashMap" => {
+                    let (k, v) = double(args).unwrap();
+                    let k_name = resolve_map_component(ctx, e, simple_type(k));
+                    let v_name = resolve_map_component(ctx, e, simple_type(v));
+                    e.map_field(site, field_name, &k_name, &v_name, id);
+                }
+                "Option" => {
+                    let single = singular(args).unwrap();
+                    match single {
+                        TypeRef::Normal(name) => {
+                            if let Some(simple) = e.scalar_type_name(name) {
+                                e.optional_field(site, field_name, simple, id);
+                            } else {
+                                // Not a scalar: no representation was ever defined
+                                // for this case, so fall back to the (deliberately
+                                // nonsensical) generic type name rather than panic.
+                                e.scalar_field(site, field_name, ty, id);
+                            }
+                        }
+                        TypeRef::Generic(_, _) => panic!(),
+                    }
+                }
+                _ => panic!(),
+            }
+        }
+    }
+}
+
+/// Whether `name` (resolved against the bundle from `ctx`'s module) is a
+/// fieldless enum that `e` renders as its native enum construct, rather
+/// than a message type.
+fn is_native_enum<B: Backend>(ctx: &ModuleContext, e: &B, name: &str) -> bool {
+    if !e.supports_native_enum() {
+        return false;
+    }
+    let ty = match ctx.bundle.resolve(ctx.module_name, name) {
+        Resolution::Local => &ctx.ir().types[name],
+        Resolution::Foreign(module) => &ctx.bundle.module(&module).types[name],
+        Resolution::Undefined => return false,
+    };
+    matches!(ty, Ty::Enum(item) if item.variants.iter().all(|(_, fields, _, _)| is_fieldless(fields)))
+}
+
+fn resolve_map_component<B: Backend>(ctx: &ModuleContext, e: &B, name: &str) -> String {
+    match e.scalar_type_name(name) {
+        Some(simple) => simple.to_string(),
+        None => resolve_type_name(ctx, e, name),
+    }
+}
+
+fn simple_type(ty: &TypeRef) -> &str {
+    match ty {
+        TypeRef::Normal(ty) => ty,
+        TypeRef::Generic(_, _) => panic!(),
+    }
+}
+
+fn singular<T>(args: &[T]) -> Option<&T> {
+    if args.len() == 1 {
+        Some(&args[0])
+    } else {
+        None
+    }
+}
+
+fn double<T>(args: &[T]) -> Option<(&T, &T)> {
+    if args.len() == 2 {
+        Some((&args[0], &args[1]))
+    } else {
+        None
+    }
+}
+
+fn emit_struct<B: Backend>(ctx: &ModuleContext, name: &str, item: &StructTy, e: &mut B) {
+    emit_fields(ctx, name, &item.fields, &item.reserved, e)
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+
+/// A reference to a type as it appears in a field: either a plain named
+/// type (`u32`, `String`, `Foo`) or one instantiated with type arguments
+/// (`Vec<T>`, `Option<T>`, `HashMap<K, V>`, or a user generic `Pair<A, B>`).
+#[derive(Clone)]
+pub(crate) enum TypeRef {
+    Normal(String),
+    Generic(String, Vec<TypeRef>),
+}
+
+pub(crate) struct IrModule {
+    pub(crate) types: HashMap<String, Ty>,
+    pub(crate) types_in_order: Vec<String>,
+    pub(crate) generics: HashMap<String, GenericDef>,
+}
+
+/// A generic `struct`/`enum` definition, kept aside from `types` until
+/// `resolve_generics` specializes it at each instantiation site.
+pub(crate) struct GenericDef {
+    pub(crate) params: Vec<String>,
+    pub(crate) body: Ty,
+}
+
+#[derive(Clone)]
+pub(crate) enum Ty {
+    Struct(StructTy),
+    Enum(EnumTy),
+}
+
+#[derive(Clone)]
+pub(crate) struct StructTy {
+    pub(crate) fields: Fields,
+    /// Field numbers reserved by a type-level `#[proto(reserved = "...")]`
+    /// attribute, as inclusive `(start, end)` ranges.
+    pub(crate) reserved: Vec<(u32, u32)>,
+}
+
+#[derive(Clone)]
+pub(crate) struct EnumTy {
+    /// Name, fields, (for a fieldless variant) the explicit Rust
+    /// discriminant it was assigned (`Variant = N`), if any, and the
+    /// explicit `#[proto(id = N)]` case number, if any.
+    pub(crate) variants: Vec<(String, Fields, Option<i64>, Option<u32>)>,
+    /// Field numbers reserved by a type-level `#[proto(reserved = "...")]`
+    /// attribute, as inclusive `(start, end)` ranges.
+    pub(crate) reserved: Vec<(u32, u32)>,
+}
+
+#[derive(Clone)]
+pub(crate) enum Fields {
+    /// Each element pairs a field's type with its explicit
+    /// `#[proto(id = N)]` number, if pinned.
+    Tuple(Vec<(TypeRef, Option<u32>)>),
+    Struct(Vec<(String, TypeRef, Option<u32>)>),
+}
+
+impl Fields {
+    pub(crate) fn singleton(&self) -> Option<&TypeRef> {
+        match self {
+            Fields::Tuple(fields) => {
+                if fields.len() == 1 {
+                    Some(&fields[0].0)
+                } else {
+                    None
+                }
+            }
+            Fields::Struct(fields) => {
+                if fields.len() == 1 {
+                    Some(&fields[0].1)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl IrModule {
+    pub(crate) fn new() -> IrModule {
+        IrModule {
+            types: HashMap::new(),
+            types_in_order: Vec::new(),
+            generics: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add_type(&mut self, name: String, ty: Ty) {
+        self.types.insert(name.clone(), ty);
+        self.types_in_order.push(name);
+    }
+
+    pub(crate) fn add_generic(&mut self, name: String, def: GenericDef) {
+        self.generics.insert(name, def);
+    }
+}
+
+/// Specializes every generic definition into one concrete message per
+/// instantiation discovered among field types, then rewrites all
+/// `TypeRef::Generic` occurrences that name a generic definition into
+/// `TypeRef::Normal` references to the mangled, monomorphized type.
+///
+/// Collection runs to a fixpoint since a freshly specialized type can
+/// itself contain references to other generics (e.g.
+/// `Pair<Wrapper<u32>, String>`); the rewrite pass only happens once that
+/// fixpoint is reached, so a generic reference nested inside a
+/// freshly-specialized body (rather than at a field's own use site) is
+/// still `TypeRef::Generic` when the next collection pass looks for it,
+/// instead of having already been rewritten to a dangling mangled name.
+pub(crate) fn resolve_generics(ir: &mut IrModule) {
+    if ir.generics.is_empty() {
+        return;
+    }
+
+    loop {
+        let mut instantiations: Vec<(String, Vec<TypeRef>)> = Vec::new();
+        for name in &ir.types_in_order {
+            collect_generic_instantiations(&ir.types[name], &ir.generics, &mut instantiations);
+        }
+
+        let mut added_any = false;
+        for (name, args) in instantiations {
+            let mangled = mangle_generic_name(&name, &args);
+            if ir.types.contains_key(&mangled) {
+                continue;
+            }
+
+            let def = &ir.generics[&name];
+            let subst: HashMap<String, TypeRef> =
+                def.params.iter().cloned().zip(args).collect();
+            let specialized = substitute_ty(&def.body, &subst);
+            ir.add_type(mangled, specialized);
+            added_any = true;
+        }
+
+        if !added_any {
+            break;
+        }
+    }
+
+    for name in ir.types_in_order.clone() {
+        let ty = ir.types.get_mut(&name).unwrap();
+        rewrite_generic_refs_ty(ty, &ir.generics);
+    }
+}
+
+fn collect_generic_instantiations(
+    ty: &Ty,
+    generics: &HashMap<String, GenericDef>,
+    out: &mut Vec<(String, Vec<TypeRef>)>,
+) {
+    match ty {
+        Ty::Struct(s) => collect_generic_instantiations_fields(&s.fields, generics, out),
+        Ty::Enum(e) => {
+            for (_, fields, _, _) in &e.variants {
+                collect_generic_instantiations_fields(fields, generics, out);
+            }
+        }
+    }
+}
+
+fn collect_generic_instantiations_fields(
+    fields: &Fields,
+    generics: &HashMap<String, GenericDef>,
+    out: &mut Vec<(String, Vec<TypeRef>)>,
+) {
+    match fields {
+        Fields::Tuple(fields) => {
+            for (ty, _) in fields {
+                collect_generic_instantiations_ref(ty, generics, out);
+            }
+        }
+        Fields::Struct(fields) => {
+            for (_, ty, _) in fields {
+                collect_generic_instantiations_ref(ty, generics, out);
+            }
+        }
+    }
+}
+
+fn collect_generic_instantiations_ref(
+    ty: &TypeRef,
+    generics: &HashMap<String, GenericDef>,
+    out: &mut Vec<(String, Vec<TypeRef>)>,
+) {
+    if let TypeRef::Generic(name, args) = ty {
+        for arg in args {
+            collect_generic_instantiations_ref(arg, generics, out);
+        }
+        if generics.contains_key(name) && !out.iter().any(|(n, a)| n == name && type_refs_eq(a, args)) {
+            out.push((name.clone(), args.clone()));
+        }
+    }
+}
+
+fn type_refs_eq(a: &[TypeRef], b: &[TypeRef]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| type_ref_eq(x, y))
+}
+
+fn type_ref_eq(a: &TypeRef, b: &TypeRef) -> bool {
+    match (a, b) {
+        (TypeRef::Normal(a), TypeRef::Normal(b)) => a == b,
+        (TypeRef::Generic(a, aa), TypeRef::Generic(b, ba)) => a == b && type_refs_eq(aa, ba),
+        _ => false,
+    }
+}
+
+fn mangle_generic_name(name: &str, args: &[TypeRef]) -> String {
+    let mut s = capitalize(name);
+    for arg in args {
+        s.push_str(&mangle_type_ref(arg));
+    }
+    s
+}
+
+fn mangle_type_ref(ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::Normal(name) => capitalize(name),
+        TypeRef::Generic(name, args) => mangle_generic_name(name, args),
+    }
+}
+
+pub(crate) fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+fn substitute_ty(ty: &Ty, subst: &HashMap<String, TypeRef>) -> Ty {
+    match ty {
+        Ty::Struct(s) => Ty::Struct(StructTy {
+            fields: substitute_fields(&s.fields, subst),
+            reserved: s.reserved.clone(),
+        }),
+        Ty::Enum(e) => Ty::Enum(EnumTy {
+            variants: e.variants.iter().map(|(name, fields, discriminant, id)| {
+                (name.clone(), substitute_fields(fields, subst), *discriminant, *id)
+            }).collect(),
+            reserved: e.reserved.clone(),
+        }),
+    }
+}
+
+fn substitute_fields(fields: &Fields, subst: &HashMap<String, TypeRef>) -> Fields {
+    match fields {
+        Fields::Tuple(fields) => {
+            Fields::Tuple(fields.iter().map(|(ty, id)| (substitute_type_ref(ty, subst), *id)).collect())
+        }
+        Fields::Struct(fields) => {
+            Fields::Struct(fields.iter().map(|(name, ty, id)| {
+                (name.clone(), substitute_type_ref(ty, subst), *id)
+            }).collect())
+        }
+    }
+}
+
+fn substitute_type_ref(ty: &TypeRef, subst: &HashMap<String, TypeRef>) -> TypeRef {
+    match ty {
+        TypeRef::Normal(name) => subst.get(name).cloned().unwrap_or_else(|| TypeRef::Normal(name.clone())),
+        TypeRef::Generic(name, args) => {
+            TypeRef::Generic(name.clone(), args.iter().map(|a| substitute_type_ref(a, subst)).collect())
+        }
+    }
+}
+
+fn rewrite_generic_refs_ty(ty: &mut Ty, generics: &HashMap<String, GenericDef>) {
+    match ty {
+        Ty::Struct(s) => rewrite_generic_refs_fields(&mut s.fields, generics),
+        Ty::Enum(e) => {
+            for (_, fields, _, _) in &mut e.variants {
+                rewrite_generic_refs_fields(fields, generics);
+            }
+        }
+    }
+}
+
+fn rewrite_generic_refs_fields(fields: &mut Fields, generics: &HashMap<String, GenericDef>) {
+    match fields {
+        Fields::Tuple(fields) => {
+            for (ty, _) in fields {
+                rewrite_generic_refs_ref(ty, generics);
+            }
+        }
+        Fields::Struct(fields) => {
+            for (_, ty, _) in fields {
+                rewrite_generic_refs_ref(ty, generics);
+            }
+        }
+    }
+}
+
+fn rewrite_generic_refs_ref(ty: &mut TypeRef, generics: &HashMap<String, GenericDef>) {
+    if let TypeRef::Generic(name, args) = ty {
+        for arg in args.iter_mut() {
+            rewrite_generic_refs_ref(arg, generics);
+        }
+        if generics.contains_key(name) {
+            let mangled = mangle_generic_name(name, args);
+            *ty = TypeRef::Normal(mangled);
+        }
+    }
+}
+
+/// Parses a `#[proto(reserved = "2,5-7")]` spec into inclusive `(start,
+/// end)` ranges, e.g. `"2,5-7"` -> `[(2, 2), (5, 7)]`.
+pub(crate) fn parse_reserved_ranges(spec: &str) -> Vec<(u32, u32)> {
+    spec.split(',').map(|part| {
+        let part = part.trim();
+        match part.split_once('-') {
+            Some((start, end)) => (start.trim().parse().unwrap(), end.trim().parse().unwrap()),
+            None => {
+                let n = part.parse().unwrap();
+                (n, n)
+            }
+        }
+    }).collect()
+}
+
+/// The inverse of `parse_reserved_ranges`: renders ranges back into a
+/// `#[proto(reserved = "...")]` spec, e.g. `[(2, 2), (5, 7)]` -> `"2,5-7"`.
+pub(crate) fn format_reserved_ranges(ranges: &[(u32, u32)]) -> String {
+    ranges.iter().map(|(start, end)| {
+        if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        }
+    }).collect::<Vec<_>>().join(",")
+}
+
+/// Converts `CamelCase`/`PascalCase` to `snake_case`.
+pub(crate) fn to_underscore_case(name: &str) -> String {
+    let mut s = String::new();
+    for ch in name.chars() {
+        if ch.is_uppercase() {
+            if !s.is_empty() {
+                s.push('_');
+            }
+            s.push_str(&ch.to_lowercase().to_string());
+        } else {
+            s.push(ch);
+        }
+    }
+    s
+}
@@ -0,0 +1,357 @@
+//! Parses a `.proto` file into an `IrModule`, the inverse of `proto_emit`.
+//!
+//! Only the subset of proto2 syntax that `proto_emit` itself produces is
+//! understood: flat `message`/native `enum` blocks, `repeated` fields,
+//! `map<K, V>` fields, `reserved N, M to K;` ranges, and the
+//! `oneof {name}_value { ... }` idiom used to represent `Option<T>`. A
+//! message whose only content is a single oneof named after the message
+//! itself (the `emit_enum` idiom) is reconstructed as an `IrModule` enum
+//! rather than a struct.
+
+use crate::ir::{EnumTy, Fields, IrModule, StructTy, Ty, TypeRef};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(String),
+    Str(String),
+    Punct(char),
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Num(chars[start..i].iter().collect()));
+        } else if c == '"' {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            tokens.push(Token::Str(chars[start..i].iter().collect()));
+            i += 1;
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].clone();
+        self.pos += 1;
+        t
+    }
+
+    fn expect_punct(&mut self, c: char) {
+        match self.next() {
+            Token::Punct(p) if p == c => {}
+            other => panic!("expected '{}', found {:?}", c, other),
+        }
+    }
+
+    fn expect_ident(&mut self) -> String {
+        match self.next() {
+            Token::Ident(s) => s,
+            other => panic!("expected identifier, found {:?}", other),
+        }
+    }
+
+    fn skip_until(&mut self, c: char) {
+        while self.next() != Token::Punct(c) {}
+    }
+
+    fn at_punct(&self, c: char) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == c)
+    }
+}
+
+/// A flat message or enum, as it appeared in the source (before the
+/// enum-via-oneof idiom is folded back together).
+enum RawItem {
+    Message(String, Fields, Vec<(u32, u32)>),
+    Enum(String, EnumTy),
+}
+
+/// The case oneof's name plus its variants, returned by `parse_message_body`
+/// when a message body turns out to be the `emit_enum` idiom.
+type EnumCase = (String, Vec<(String, Fields, Option<i64>, Option<u32>)>);
+
+pub(crate) fn load_proto_file(text: &str) -> IrModule {
+    let tokens = tokenize(text);
+    let mut p = Parser { tokens, pos: 0 };
+
+    let mut raw_items: Vec<RawItem> = Vec::new();
+    while p.peek().is_some() {
+        let kw = p.expect_ident();
+        match kw.as_str() {
+            "syntax" | "package" | "option" | "import" => p.skip_until(';'),
+            "message" => {
+                let name = p.expect_ident();
+                let (fields, case, reserved) = parse_message_body(&mut p, &name);
+                if let Some((_case_name, variants)) = case {
+                    raw_items.push(RawItem::Enum(name, EnumTy { variants, reserved }));
+                } else {
+                    raw_items.push(RawItem::Message(name, fields, reserved));
+                }
+            }
+            "enum" => {
+                let name = p.expect_ident();
+                raw_items.push(RawItem::Enum(name, parse_native_enum_body(&mut p)));
+            }
+            other => panic!("unexpected top-level item `{}`", other),
+        }
+    }
+
+    assemble_ir(raw_items)
+}
+
+/// Parses a `message { ... }` body. Returns the plain fields, if the body
+/// turned out to be the `emit_enum` idiom (a single oneof named after the
+/// message, with no other fields) the oneof's case variants, and any
+/// `reserved ...;` ranges declared directly in the body.
+fn parse_message_body(p: &mut Parser, message_name: &str) -> (Fields, Option<EnumCase>, Vec<(u32, u32)>) {
+    p.expect_punct('{');
+
+    let mut fields: Vec<(String, TypeRef, Option<u32>)> = Vec::new();
+    let mut case: Option<EnumCase> = None;
+    let mut reserved: Vec<(u32, u32)> = Vec::new();
+
+    while !p.at_punct('}') {
+        match p.peek().cloned() {
+            Some(Token::Ident(kw)) if kw == "reserved" => {
+                p.pos += 1;
+                reserved.extend(parse_reserved_line(p));
+            }
+            Some(Token::Ident(kw)) if kw == "oneof" => {
+                p.pos += 1;
+                let oneof_name = p.expect_ident();
+                let oneof_fields = parse_oneof_body(p);
+
+                if oneof_name == crate::ir::to_underscore_case(message_name) && fields.is_empty() {
+                    let variants = oneof_fields.into_iter().map(|(name, ty, num)| {
+                        (name, Fields::Tuple(vec![(ty, None)]), None, Some(num))
+                    }).collect();
+                    case = Some((oneof_name, variants));
+                } else {
+                    // `Option<T>` idiom: a single-field oneof named `{field}_value`.
+                    assert_eq!(oneof_fields.len(), 1);
+                    let (name, ty, num) = oneof_fields.into_iter().next().unwrap();
+                    fields.push((name, TypeRef::Generic("Option".to_string(), vec![ty]), Some(num)));
+                }
+            }
+            _ => {
+                let (name, ty, num) = parse_field(p);
+                fields.push((name, ty, Some(num)));
+            }
+        }
+    }
+    p.expect_punct('}');
+
+    (Fields::Struct(fields), case, reserved)
+}
+
+/// Parses a `reserved 2, 5 to 7;` line into its inclusive `(start, end)`
+/// ranges.
+fn parse_reserved_line(p: &mut Parser) -> Vec<(u32, u32)> {
+    let mut ranges = Vec::new();
+    loop {
+        let start: u32 = match p.next() {
+            Token::Num(n) => n.parse().unwrap(),
+            other => panic!("expected reserved number, found {:?}", other),
+        };
+        let end = if matches!(p.peek(), Some(Token::Ident(kw)) if kw == "to") {
+            p.pos += 1;
+            match p.next() {
+                Token::Num(n) => n.parse().unwrap(),
+                other => panic!("expected reserved range end, found {:?}", other),
+            }
+        } else {
+            start
+        };
+        ranges.push((start, end));
+        if p.at_punct(',') {
+            p.pos += 1;
+        } else {
+            break;
+        }
+    }
+    p.expect_punct(';');
+    ranges
+}
+
+fn parse_oneof_body(p: &mut Parser) -> Vec<(String, TypeRef, u32)> {
+    p.expect_punct('{');
+    let mut fields = Vec::new();
+    while !p.at_punct('}') {
+        fields.push(parse_field(p));
+    }
+    p.expect_punct('}');
+    fields
+}
+
+/// Parses one `[repeated] Type name = N [annotations];` line, returning its
+/// field number along with its name and type so the caller can round-trip
+/// it as an explicit `#[proto(id = N)]` pin.
+fn parse_field(p: &mut Parser) -> (String, TypeRef, u32) {
+    let mut repeated = false;
+    let mut first = p.expect_ident();
+    if first == "repeated" {
+        repeated = true;
+        first = p.expect_ident();
+    }
+
+    let ty = if first == "map" {
+        p.expect_punct('<');
+        let k = parse_type_name(p);
+        p.expect_punct(',');
+        let v = parse_type_name(p);
+        p.expect_punct('>');
+        TypeRef::Generic("HashMap".to_string(), vec![reverse_scalar(&k), reverse_scalar(&v)])
+    } else {
+        reverse_scalar(&first)
+    };
+
+    let name = p.expect_ident();
+    p.expect_punct('=');
+    let number: u32 = match p.next() {
+        Token::Num(n) => n.parse().unwrap(),
+        other => panic!("expected field number, found {:?}", other),
+    };
+
+    // Optional `[(gogoproto.nullable)=false]`-style annotations.
+    if p.at_punct('[') {
+        p.skip_until(']');
+    }
+    p.expect_punct(';');
+
+    let ty = if repeated {
+        TypeRef::Generic("Vec".to_string(), vec![ty])
+    } else {
+        ty
+    };
+
+    (name, ty, number)
+}
+
+/// Parses a bare type name appearing inside `map<K, V>`.
+fn parse_type_name(p: &mut Parser) -> String {
+    p.expect_ident()
+}
+
+fn reverse_scalar(name: &str) -> TypeRef {
+    match name {
+        "uint32" => TypeRef::Normal("u32".to_string()),
+        "uint64" => TypeRef::Normal("u64".to_string()),
+        "int32" => TypeRef::Normal("i32".to_string()),
+        "int64" => TypeRef::Normal("i64".to_string()),
+        "float" => TypeRef::Normal("f32".to_string()),
+        "double" => TypeRef::Normal("f64".to_string()),
+        "bool" => TypeRef::Normal("bool".to_string()),
+        "string" => TypeRef::Normal("String".to_string()),
+        "bytes" => TypeRef::Generic("Vec".to_string(), vec![TypeRef::Normal("u8".to_string())]),
+        other => TypeRef::Normal(other.to_string()),
+    }
+}
+
+fn parse_native_enum_body(p: &mut Parser) -> EnumTy {
+    p.expect_punct('{');
+    let mut variants = Vec::new();
+    let mut reserved: Vec<(u32, u32)> = Vec::new();
+    while !p.at_punct('}') {
+        if matches!(p.peek(), Some(Token::Ident(kw)) if kw == "reserved") {
+            p.pos += 1;
+            reserved.extend(parse_reserved_line(p));
+            continue;
+        }
+        let name = p.expect_ident();
+        p.expect_punct('=');
+        let discriminant = match p.next() {
+            Token::Num(n) => n.parse().unwrap(),
+            other => panic!("expected enum discriminant, found {:?}", other),
+        };
+        if p.at_punct('[') {
+            p.skip_until(']');
+        }
+        p.expect_punct(';');
+        variants.push((name, Fields::Struct(Vec::new()), Some(discriminant), None));
+    }
+    p.expect_punct('}');
+    EnumTy { variants, reserved }
+}
+
+/// Folds the nested payload messages (`{EnumName}{VariantName}`) emitted
+/// alongside an enum's case oneof back into that enum's variants, and
+/// drops them from the module's top-level types.
+fn assemble_ir(raw_items: Vec<RawItem>) -> IrModule {
+    let mut messages: std::collections::HashMap<String, Fields> = std::collections::HashMap::new();
+    for item in &raw_items {
+        if let RawItem::Message(name, fields, _) = item {
+            messages.insert(name.clone(), fields.clone());
+        }
+    }
+
+    let mut ir = IrModule::new();
+    let mut absorbed: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    for item in &raw_items {
+        if let RawItem::Enum(name, enum_ty) = item {
+            for (variant_name, _, _, _) in &enum_ty.variants {
+                absorbed.insert(format!("{}{}", name, variant_name));
+            }
+        }
+    }
+
+    for item in raw_items {
+        match item {
+            RawItem::Message(name, _, _) if absorbed.contains(&name) => {}
+            RawItem::Message(name, fields, reserved) => ir.add_type(name, Ty::Struct(StructTy { fields, reserved })),
+            RawItem::Enum(name, enum_ty) => {
+                let reserved = enum_ty.reserved;
+                let variants = enum_ty.variants.into_iter().map(|(variant_name, fields, discriminant, id)| {
+                    let payload_name = format!("{}{}", name, variant_name);
+                    if let Some(payload) = messages.get(&payload_name) {
+                        (variant_name, payload.clone(), discriminant, id)
+                    } else {
+                        (variant_name, fields, discriminant, id)
+                    }
+                }).collect();
+                ir.add_type(name, Ty::Enum(EnumTy { variants, reserved }));
+            }
+        }
+    }
+
+    ir
+}
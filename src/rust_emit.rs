@@ -0,0 +1,90 @@
+//! Emits Rust `struct`/`enum` definitions from an `IrModule`, the inverse
+//! of `rust_load`.
+
+use crate::ir::{format_reserved_ranges, EnumTy, Fields, IrModule, StructTy, Ty, TypeRef};
+
+pub(crate) fn emit_rust(ir: &IrModule) -> String {
+    let mut out = String::new();
+    for name in &ir.types_in_order {
+        match &ir.types[name] {
+            Ty::Struct(item) => emit_struct(name, item, &mut out),
+            Ty::Enum(item) => emit_enum(name, item, &mut out),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn emit_reserved_attr(reserved: &[(u32, u32)], out: &mut String) {
+    if !reserved.is_empty() {
+        out.push_str(&format!("#[proto(reserved = \"{}\")]\n", format_reserved_ranges(reserved)));
+    }
+}
+
+fn emit_struct(name: &str, item: &StructTy, out: &mut String) {
+    emit_reserved_attr(&item.reserved, out);
+    out.push_str(&format!("struct {} {{\n", name));
+    emit_fields_body(&item.fields, out);
+    out.push_str("}\n");
+}
+
+fn emit_enum(name: &str, item: &EnumTy, out: &mut String) {
+    emit_reserved_attr(&item.reserved, out);
+    out.push_str(&format!("enum {} {{\n", name));
+    for (variant_name, fields, discriminant, id) in &item.variants {
+        if let Some(id) = id {
+            out.push_str(&format!("    #[proto(id = {})]\n", id));
+        }
+        out.push_str(&format!("    {}", variant_name));
+        emit_variant_fields(fields, out);
+        if let Some(discriminant) = discriminant {
+            out.push_str(&format!(" = {}", discriminant));
+        }
+        out.push_str(",\n");
+    }
+    out.push_str("}\n");
+}
+
+fn emit_variant_fields(fields: &Fields, out: &mut String) {
+    match fields {
+        Fields::Tuple(types) => {
+            if !types.is_empty() {
+                let types = types.iter().map(|(ty, _)| emit_type_ref(ty)).collect::<Vec<_>>().join(", ");
+                out.push_str(&format!("({})", types));
+            }
+        }
+        Fields::Struct(fields) => {
+            if !fields.is_empty() {
+                let fields = fields.iter()
+                    .map(|(name, ty, _)| format!("{}: {}", name, emit_type_ref(ty)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                out.push_str(&format!(" {{ {} }}", fields));
+            }
+        }
+    }
+}
+
+fn emit_fields_body(fields: &Fields, out: &mut String) {
+    match fields {
+        Fields::Tuple(_) => panic!("tuple structs are not supported"),
+        Fields::Struct(fields) => {
+            for (name, ty, id) in fields {
+                if let Some(id) = id {
+                    out.push_str(&format!("    #[proto(id = {})]\n", id));
+                }
+                out.push_str(&format!("    {}: {},\n", name, emit_type_ref(ty)));
+            }
+        }
+    }
+}
+
+fn emit_type_ref(ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::Normal(name) => name.clone(),
+        TypeRef::Generic(name, args) => {
+            let args = args.iter().map(emit_type_ref).collect::<Vec<_>>().join(", ");
+            format!("{}<{}>", name, args)
+        }
+    }
+}
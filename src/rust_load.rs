@@ -0,0 +1,145 @@
+//! Loads a Rust source file (parsed by `syn`) into an `IrModule`.
+
+use crate::ir::{parse_reserved_ranges, EnumTy, Fields, GenericDef, IrModule, StructTy, Ty, TypeRef};
+
+pub(crate) fn load_proto(file: &syn::File) -> IrModule {
+    let mut ir = IrModule::new();
+    for item in &file.items {
+        match item {
+            syn::Item::Enum(item) => {
+                let params = generic_params(&item.generics);
+                let ty = Ty::Enum(load_enum(item));
+                if params.is_empty() {
+                    ir.add_type(item.ident.to_string(), ty);
+                } else {
+                    ir.add_generic(item.ident.to_string(), GenericDef { params, body: ty });
+                }
+            }
+            syn::Item::Struct(item) => {
+                let params = generic_params(&item.generics);
+                let ty = Ty::Struct(load_struct(item));
+                if params.is_empty() {
+                    ir.add_type(item.ident.to_string(), ty);
+                } else {
+                    ir.add_generic(item.ident.to_string(), GenericDef { params, body: ty });
+                }
+            }
+            _ => panic!("only enums and structs allowed"),
+        }
+    }
+    ir
+}
+
+fn generic_params(generics: &syn::Generics) -> Vec<String> {
+    generics.params.iter().map(|p| {
+        match p {
+            syn::GenericParam::Type(t) => t.ident.to_string(),
+            _ => panic!("only type parameters are supported in generics"),
+        }
+    }).collect()
+}
+
+/// The contents of a `#[proto(...)]` attribute, as found on a field,
+/// variant, struct, or enum.
+#[derive(Default)]
+struct ProtoAttr {
+    id: Option<u32>,
+    reserved: Option<String>,
+}
+
+fn parse_proto_attrs(attrs: &[syn::Attribute]) -> ProtoAttr {
+    let mut result = ProtoAttr::default();
+    for attr in attrs {
+        if !attr.path().is_ident("proto") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                let lit: syn::LitInt = meta.value()?.parse()?;
+                result.id = Some(lit.base10_parse()?);
+                Ok(())
+            } else if meta.path.is_ident("reserved") {
+                let lit: syn::LitStr = meta.value()?.parse()?;
+                result.reserved = Some(lit.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown `#[proto(...)]` attribute"))
+            }
+        }).unwrap();
+    }
+    result
+}
+
+fn reserved_ranges(attrs: &[syn::Attribute]) -> Vec<(u32, u32)> {
+    match parse_proto_attrs(attrs).reserved {
+        Some(spec) => parse_reserved_ranges(&spec),
+        None => Vec::new(),
+    }
+}
+
+fn load_struct(item: &syn::ItemStruct) -> StructTy {
+    StructTy {
+        fields: load_fields(&item.fields),
+        reserved: reserved_ranges(&item.attrs),
+    }
+}
+
+fn load_fields(fields: &syn::Fields) -> Fields {
+    if fields.iter().all(|f| f.ident.is_some()) {
+        Fields::Struct(
+            fields.iter().map(|f| {
+                let id = parse_proto_attrs(&f.attrs).id;
+                (f.ident.as_ref().unwrap().to_string(), type_ref(&f.ty), id)
+            }).collect()
+        )
+    } else {
+        Fields::Tuple(
+            fields.iter().map(|f| {
+                let id = parse_proto_attrs(&f.attrs).id;
+                (type_ref(&f.ty), id)
+            }).collect()
+        )
+    }
+}
+
+fn type_ref(ty: &syn::Type) -> TypeRef {
+    match ty {
+        syn::Type::Path(p) => {
+            assert!(p.qself.is_none());
+            assert_eq!(p.path.segments.len(), 1);
+
+            let seg = &p.path.segments[0];
+            match &seg.arguments {
+                syn::PathArguments::None => TypeRef::Normal(seg.ident.to_string()),
+                syn::PathArguments::AngleBracketed(args) => {
+                    TypeRef::Generic(seg.ident.to_string(), args.args.iter().map(|a| {
+                        match a {
+                            syn::GenericArgument::Type(ty) => type_ref(ty),
+                            _ => panic!(),
+                        }
+                    }).collect())
+                }
+                syn::PathArguments::Parenthesized(_) => todo!(),
+            }
+        }
+        _ => panic!("other types not allowed (yet)"),
+    }
+}
+
+fn load_enum(item: &syn::ItemEnum) -> EnumTy {
+    EnumTy {
+        variants: item.variants.iter().map(|v| {
+            let discriminant = v.discriminant.as_ref().map(|(_, expr)| load_discriminant(expr));
+            let id = parse_proto_attrs(&v.attrs).id;
+            (v.ident.to_string(), load_fields(&v.fields), discriminant, id)
+        }).collect(),
+        reserved: reserved_ranges(&item.attrs),
+    }
+}
+
+fn load_discriminant(expr: &syn::Expr) -> i64 {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => lit.base10_parse().unwrap(),
+        _ => panic!("only integer literal discriminants are supported"),
+    }
+}
@@ -0,0 +1,735 @@
+//! Generates self-contained protobuf-wire-format `encode_*`/`decode_*`
+//! functions for every `IrModule` type, so the generated code needs no
+//! external `protoc` step at build time.
+//!
+//! Field numbers are assigned the same way `proto_emit` assigns them:
+//! honoring any explicit `#[proto(id = N)]` pins and `reserved` ranges via
+//! `emit::assign_numbers`, and auto-filling the rest in declaration order,
+//! so the generated wire format matches whatever `.proto` schema
+//! `proto_emit` produced for the same module.
+//!
+//! A fieldless enum's value is written as the variant's 0-based position
+//! in the Rust declaration, not the number `proto_emit` gives it in the
+//! `.proto` `enum`, so the two differ whenever an explicit `Variant = N`
+//! discriminant moves a variant off its position.
+
+use crate::emit::assign_numbers;
+use crate::ir::{to_underscore_case, EnumTy, Fields, IrModule, StructTy, Ty, TypeRef};
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_64BIT: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_32BIT: u8 = 5;
+
+const PRELUDE: &str = r#"fn write_tag(buf: &mut Vec<u8>, field_number: u32, wire_type: u8) {
+    write_varint(buf, ((field_number as u64) << 3) | wire_type as u64);
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> u64 {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = buf[*pos];
+        *pos += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    result
+}
+
+fn read_tag(buf: &[u8], pos: &mut usize) -> (u32, u8) {
+    let tag = read_varint(buf, pos);
+    ((tag >> 3) as u32, (tag & 0x7) as u8)
+}
+
+fn skip_field(buf: &[u8], pos: &mut usize, wire_type: u8) {
+    match wire_type {
+        0 => { read_varint(buf, pos); }
+        1 => { *pos += 8; }
+        2 => { let len = read_varint(buf, pos) as usize; *pos += len; }
+        5 => { *pos += 4; }
+        _ => panic!("unknown wire type {}", wire_type),
+    }
+}
+
+"#;
+
+/// How a single (non-repeated, non-optional) value is represented on the
+/// wire, independent of whether it's a plain field, a `Vec` element, a
+/// `HashMap` key/value, or an enum case.
+enum Prim {
+    Varint(&'static str),
+    Fixed32,
+    Fixed64,
+    Bytes,
+    /// A nested message/oneof: wire type 2, length-delimited, encoded by
+    /// calling that type's own `encode_*`/`decode_*` functions.
+    Message(String),
+    /// A fieldless enum: wire type 0, the variant's 0-based index.
+    EnumDiscriminant(String, Vec<String>),
+}
+
+pub(crate) fn emit_wire_codec(ir: &IrModule) -> String {
+    let mut out = String::new();
+    out.push_str(PRELUDE);
+    for name in &ir.types_in_order {
+        match &ir.types[name] {
+            Ty::Struct(item) => emit_struct_codec(ir, name, item, &mut out),
+            Ty::Enum(item) => emit_enum_codec(ir, name, item, &mut out),
+        }
+    }
+    out
+}
+
+fn struct_fields(fields: &Fields) -> &Vec<(String, TypeRef, Option<u32>)> {
+    match fields {
+        Fields::Struct(fields) => fields,
+        Fields::Tuple(_) => panic!("tuple structs are not supported"),
+    }
+}
+
+fn is_fieldless(fields: &Fields) -> bool {
+    match fields {
+        Fields::Tuple(f) => f.is_empty(),
+        Fields::Struct(f) => f.is_empty(),
+    }
+}
+
+fn classify(ir: &IrModule, name: &str) -> Prim {
+    match name {
+        "u8" => Prim::Varint("u8"),
+        "u16" => Prim::Varint("u16"),
+        "u32" => Prim::Varint("u32"),
+        "u64" => Prim::Varint("u64"),
+        "i8" => Prim::Varint("i8"),
+        "i16" => Prim::Varint("i16"),
+        "i32" => Prim::Varint("i32"),
+        "i64" => Prim::Varint("i64"),
+        "bool" => Prim::Varint("bool"),
+        "f32" => Prim::Fixed32,
+        "f64" => Prim::Fixed64,
+        "String" => Prim::Bytes,
+        _ => match ir.types.get(name) {
+            Some(Ty::Enum(e)) if e.variants.iter().all(|(_, f, _, _)| is_fieldless(f)) => {
+                Prim::EnumDiscriminant(name.to_string(), e.variants.iter().map(|(n, _, _, _)| n.clone()).collect())
+            }
+            _ => Prim::Message(name.to_string()),
+        },
+    }
+}
+
+fn wire_type_of(prim: &Prim) -> u8 {
+    match prim {
+        Prim::Varint(_) | Prim::EnumDiscriminant(_, _) => WIRE_VARINT,
+        Prim::Fixed64 => WIRE_64BIT,
+        Prim::Fixed32 => WIRE_32BIT,
+        Prim::Bytes | Prim::Message(_) => WIRE_LEN,
+    }
+}
+
+fn write_value(prim: &Prim, expr: &str, buf_name: &str, out: &mut String) {
+    match prim {
+        Prim::Varint(kind) => match *kind {
+            "bool" => out.push_str(&format!("    write_varint({}, if {} {{ 1 }} else {{ 0 }});\n", buf_name, expr)),
+            "i8" | "i16" | "i32" | "i64" => {
+                out.push_str(&format!("    write_varint({}, ({} as i64) as u64);\n", buf_name, expr))
+            }
+            _ => out.push_str(&format!("    write_varint({}, {} as u64);\n", buf_name, expr)),
+        },
+        Prim::Fixed32 => out.push_str(&format!("    {}.extend_from_slice(&({}).to_le_bytes());\n", buf_name, expr)),
+        Prim::Fixed64 => out.push_str(&format!("    {}.extend_from_slice(&({}).to_le_bytes());\n", buf_name, expr)),
+        Prim::Bytes => {
+            out.push_str(&format!("    write_varint({}, ({}).len() as u64);\n", buf_name, expr));
+            out.push_str(&format!("    {}.extend_from_slice(({}).as_bytes());\n", buf_name, expr));
+        }
+        Prim::Message(ty) => {
+            let snake = to_underscore_case(ty);
+            out.push_str("    {\n");
+            out.push_str("        let mut tmp = Vec::new();\n");
+            out.push_str(&format!("        encode_{}(&({}), &mut tmp);\n", snake, expr));
+            out.push_str(&format!("        write_varint({}, tmp.len() as u64);\n", buf_name));
+            out.push_str(&format!("        {}.extend_from_slice(&tmp);\n", buf_name));
+            out.push_str("    }\n");
+        }
+        Prim::EnumDiscriminant(ty, variants) => {
+            out.push_str(&format!("    write_varint({}, match ", buf_name));
+            out.push_str(expr);
+            out.push_str(" {\n");
+            for (i, variant) in variants.iter().enumerate() {
+                out.push_str(&format!("        {}::{} => {},\n", ty, variant, i));
+            }
+            out.push_str("    } as u64);\n");
+        }
+    }
+}
+
+/// Returns a decode expression reading one value off `buf_name`/`pos_name`
+/// (plain `buf`/`pos` for a top-level field, or the per-entry temporaries
+/// used while decoding a `HashMap`'s key/value pair).
+fn read_value(prim: &Prim, buf_name: &str, pos_name: &str) -> String {
+    match prim {
+        Prim::Varint(kind) => match *kind {
+            "bool" => format!("read_varint({}, &mut {}) != 0", buf_name, pos_name),
+            "f32" | "f64" => unreachable!(),
+            "i8" | "i16" | "i32" | "i64" => format!("read_varint({}, &mut {}) as i64 as {}", buf_name, pos_name, kind),
+            _ => format!("read_varint({}, &mut {}) as {}", buf_name, pos_name, kind),
+        },
+        Prim::Fixed32 => format!(
+            "{{ let mut b = [0u8; 4]; b.copy_from_slice(&{}[{}..{} + 4]); {} += 4; f32::from_le_bytes(b) }}",
+            buf_name, pos_name, pos_name, pos_name
+        ),
+        Prim::Fixed64 => format!(
+            "{{ let mut b = [0u8; 8]; b.copy_from_slice(&{}[{}..{} + 8]); {} += 8; f64::from_le_bytes(b) }}",
+            buf_name, pos_name, pos_name, pos_name
+        ),
+        Prim::Bytes => format!(
+            "{{ let len = read_varint({}, &mut {}) as usize; let s = String::from_utf8({}[{}..{} + len].to_vec()).unwrap(); {} += len; s }}",
+            buf_name, pos_name, buf_name, pos_name, pos_name, pos_name
+        ),
+        Prim::Message(ty) => {
+            let snake = to_underscore_case(ty);
+            format!(
+                "{{ let len = read_varint({}, &mut {}) as usize; let v = decode_{}(&{}[{}..{} + len]); {} += len; v }}",
+                buf_name, pos_name, snake, buf_name, pos_name, pos_name, pos_name
+            )
+        }
+        Prim::EnumDiscriminant(ty, variants) => {
+            let mut s = String::new();
+            s.push_str(&format!("match read_varint({}, &mut {}) {{\n", buf_name, pos_name));
+            for (i, variant) in variants.iter().enumerate() {
+                s.push_str(&format!("            {} => {}::{},\n", i, ty, variant));
+            }
+            s.push_str(&format!("            other => panic!(\"unknown {} discriminant {{}}\", other),\n", ty));
+            s.push_str("        }");
+            s
+        }
+    }
+}
+
+fn rust_type_name(ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::Normal(name) => name.clone(),
+        TypeRef::Generic(name, args) => {
+            let args = args.iter().map(rust_type_name).collect::<Vec<_>>().join(", ");
+            format!("{}<{}>", name, args)
+        }
+    }
+}
+
+/// Emits the encode-side statements for one field, given an expression
+/// that evaluates to its *value* (not a reference to it, e.g. `v.count` or
+/// `*key`) and the name of the in-scope `Vec<u8>` to append to (`buf`, or a
+/// per-entry temporary when encoding a `HashMap`'s key/value pair).
+fn emit_field_encode(ir: &IrModule, expr: &str, ty: &TypeRef, id: u32, buf_name: &str, out: &mut String) {
+    match ty {
+        TypeRef::Normal(name) => {
+            let prim = classify(ir, name);
+            out.push_str(&format!("    write_tag({}, {}, {});\n", buf_name, id, wire_type_of(&prim)));
+            write_value(&prim, expr, buf_name, out);
+        }
+        TypeRef::Generic(generic, args) => match generic.as_str() {
+            "Vec" => {
+                let inner = &args[0];
+                if let TypeRef::Normal(n) = inner {
+                    if n == "u8" {
+                        out.push_str(&format!("    write_tag({}, {}, {});\n", buf_name, id, WIRE_LEN));
+                        out.push_str(&format!("    write_varint({}, ({}).len() as u64);\n", buf_name, expr));
+                        out.push_str(&format!("    {}.extend_from_slice(&{});\n", buf_name, expr));
+                        return;
+                    }
+                }
+                out.push_str(&format!("    for __item in ({}).iter() {{\n", expr));
+                emit_field_encode_indented(ir, "*__item", inner, id, buf_name, out, "    ");
+                out.push_str("    }\n");
+            }
+            "HashMap" => {
+                let (k, v) = (&args[0], &args[1]);
+                out.push_str(&format!("    for (__k, __v) in {}.iter() {{\n", expr));
+                out.push_str("        let mut __entry = Vec::new();\n");
+                out.push_str("        let __entry = &mut __entry;\n");
+                emit_field_encode_indented(ir, "*__k", k, 1, "__entry", out, "        ");
+                emit_field_encode_indented(ir, "*__v", v, 2, "__entry", out, "        ");
+                out.push_str(&format!("        write_tag({}, {}, {});\n", buf_name, id, WIRE_LEN));
+                out.push_str(&format!("        write_varint({}, __entry.len() as u64);\n", buf_name));
+                out.push_str(&format!("        {}.extend_from_slice(&__entry);\n", buf_name));
+                out.push_str("    }\n");
+            }
+            "Option" => {
+                let inner = &args[0];
+                out.push_str(&format!("    if let Some(__inner) = ({}).as_ref() {{\n", expr));
+                emit_field_encode_indented(ir, "*__inner", inner, id, buf_name, out, "    ");
+                out.push_str("    }\n");
+            }
+            other => panic!("unsupported generic field type `{}`", other),
+        },
+    }
+}
+
+fn emit_field_encode_indented(ir: &IrModule, expr: &str, ty: &TypeRef, id: u32, buf_name: &str, out: &mut String, indent: &str) {
+    let mut body = String::new();
+    emit_field_encode(ir, expr, ty, id, buf_name, &mut body);
+    for line in body.lines() {
+        out.push_str(indent);
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// Emits the decode-side `let mut` accumulator declaration for a field.
+fn emit_field_decl(ty: &TypeRef, name: &str, out: &mut String) {
+    match ty {
+        TypeRef::Normal(_) => out.push_str(&format!("    let mut __{}: Option<{}> = None;\n", name, rust_type_name(ty))),
+        TypeRef::Generic(generic, args) => match generic.as_str() {
+            "Vec" => {
+                if let TypeRef::Normal(n) = &args[0] {
+                    if n == "u8" {
+                        out.push_str(&format!("    let mut __{}: Vec<u8> = Vec::new();\n", name));
+                        return;
+                    }
+                }
+                out.push_str(&format!("    let mut __{}: {} = Vec::new();\n", name, rust_type_name(ty)));
+            }
+            "HashMap" => out.push_str(&format!("    let mut __{}: {} = std::collections::HashMap::new();\n", name, rust_type_name(ty))),
+            "Option" => out.push_str(&format!("    let mut __{}: {} = None;\n", name, rust_type_name(ty))),
+            other => panic!("unsupported generic field type `{}`", other),
+        },
+    }
+}
+
+/// Emits the statements that update a field's accumulator (`__{name}`,
+/// already declared by `emit_field_decl`) from one occurrence of its tag,
+/// assuming `buf`/`pos` are already positioned just past that tag. Shared
+/// by `emit_field_decode_arm` (a struct field, matched inside a `while`
+/// loop over repeated tags) and an enum's `Singleton` case (matched the
+/// same way, since a repeated/map payload can still occupy more than one
+/// wire entry even when it rides directly on the case's own field slot).
+fn emit_field_decode_body(ir: &IrModule, name: &str, ty: &TypeRef, out: &mut String) {
+    match ty {
+        TypeRef::Normal(n) => {
+            let prim = classify(ir, n);
+            out.push_str(&format!("                __{} = Some({});\n", name, read_value(&prim, "buf", "pos")));
+        }
+        TypeRef::Generic(generic, args) => match generic.as_str() {
+            "Vec" => {
+                if let TypeRef::Normal(n) = &args[0] {
+                    if n == "u8" {
+                        out.push_str("                let len = read_varint(buf, &mut pos) as usize;\n");
+                        out.push_str(&format!("                __{}.extend_from_slice(&buf[pos..pos + len]);\n", name));
+                        out.push_str("                pos += len;\n");
+                        return;
+                    }
+                }
+                let prim = classify(ir, &rust_type_name(&args[0]));
+                out.push_str(&format!("                __{}.push({});\n", name, read_value(&prim, "buf", "pos")));
+            }
+            "HashMap" => {
+                out.push_str("                let len = read_varint(buf, &mut pos) as usize;\n");
+                out.push_str("                let entry = &buf[pos..pos + len];\n");
+                out.push_str("                pos += len;\n");
+                out.push_str("                let mut epos = 0usize;\n");
+                out.push_str("                let mut __key = None;\n");
+                out.push_str("                let mut __val = None;\n");
+                out.push_str("                while epos < entry.len() {\n");
+                out.push_str("                    let (efield, _ewire) = read_tag(entry, &mut epos);\n");
+                let k_prim = classify(ir, &rust_type_name(&args[0]));
+                let v_prim = classify(ir, &rust_type_name(&args[1]));
+                out.push_str("                    match efield {\n");
+                out.push_str(&format!("                        1 => __key = Some({}),\n", read_value(&k_prim, "entry", "epos")));
+                out.push_str(&format!("                        2 => __val = Some({}),\n", read_value(&v_prim, "entry", "epos")));
+                out.push_str("                        _ => {}\n");
+                out.push_str("                    }\n");
+                out.push_str("                }\n");
+                out.push_str(&format!("                __{}.insert(__key.unwrap(), __val.unwrap());\n", name));
+            }
+            "Option" => {
+                let prim = classify(ir, &rust_type_name(&args[0]));
+                out.push_str(&format!("                __{} = Some({});\n", name, read_value(&prim, "buf", "pos")));
+            }
+            other => panic!("unsupported generic field type `{}`", other),
+        },
+    }
+}
+
+/// Emits the `field_number => { ... }` match arm that updates a field's
+/// accumulator when its tag is read back off the wire.
+fn emit_field_decode_arm(ir: &IrModule, name: &str, ty: &TypeRef, id: u32, out: &mut String) {
+    out.push_str(&format!("            {} => {{\n", id));
+    emit_field_decode_body(ir, name, ty, out);
+    out.push_str("            }\n");
+}
+
+/// Emits the `field_number => { ... }` match arm that decodes and
+/// immediately returns a non-accumulating `Singleton` case (i.e. one whose
+/// payload can only occupy a single wire entry, so `singleton_needs_accumulator`
+/// is false for it). A plain scalar/message `ty` goes straight through
+/// `classify`/`read_value` as before; a generic `ty` (`Vec<u8>` bytes or
+/// `Option<T>`) isn't something `classify` understands (it only knows
+/// scalar/message type *names*, not `TypeRef::Generic` shapes), so it's
+/// decoded via the same `emit_field_decl`/`emit_field_decode_body` pair a
+/// struct field would use, into a throwaway local that's already the
+/// field's own type and needs no unwrapping.
+fn emit_singleton_decode_return(
+    ir: &IrModule,
+    enum_name: &str,
+    variant_name: &str,
+    field_name: Option<&str>,
+    ty: &TypeRef,
+    id: u32,
+    out: &mut String,
+) {
+    out.push_str(&format!("            {} => {{\n", id));
+    let value = match ty {
+        TypeRef::Normal(n) => {
+            let prim = classify(ir, n);
+            read_value(&prim, "buf", "pos")
+        }
+        TypeRef::Generic(_, _) => {
+            emit_field_decl(ty, "singleton", out);
+            emit_field_decode_body(ir, "singleton", ty, out);
+            "__singleton".to_string()
+        }
+    };
+    match field_name {
+        None => out.push_str(&format!("                return {}::{}({});\n", enum_name, variant_name, value)),
+        Some(field_name) => out.push_str(&format!(
+            "                return {}::{} {{ {}: {} }};\n",
+            enum_name, variant_name, field_name, value
+        )),
+    }
+    out.push_str("            }\n");
+}
+
+fn emit_struct_codec(ir: &IrModule, name: &str, item: &StructTy, out: &mut String) {
+    let fields = struct_fields(&item.fields);
+    let snake = to_underscore_case(name);
+
+    let numbers = assign_numbers(&fields.iter().map(|(_, _, id)| *id).collect::<Vec<_>>(), &item.reserved);
+
+    out.push_str(&format!("pub fn encode_{}(v: &{}, buf: &mut Vec<u8>) {{\n", snake, name));
+    for ((field_name, ty, _), number) in fields.iter().zip(&numbers) {
+        emit_field_encode(ir, &format!("v.{}", field_name), ty, *number, "buf", out);
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(&format!("pub fn decode_{}(buf: &[u8]) -> {} {{\n", snake, name));
+    out.push_str("    let mut pos = 0usize;\n");
+    for (field_name, ty, _) in fields {
+        emit_field_decl(ty, field_name, out);
+    }
+    out.push_str("    while pos < buf.len() {\n");
+    out.push_str("        let (field_number, wire_type) = read_tag(buf, &mut pos);\n");
+    out.push_str("        match field_number {\n");
+    for ((field_name, ty, _), number) in fields.iter().zip(&numbers) {
+        emit_field_decode_arm(ir, field_name, ty, *number, out);
+    }
+    out.push_str("            _ => skip_field(buf, &mut pos, wire_type),\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str(&format!("    {} {{\n", name));
+    for (field_name, ty, _) in fields {
+        match ty {
+            TypeRef::Generic(g, _) if g == "Vec" || g == "HashMap" || g == "Option" => {
+                out.push_str(&format!("        {}: __{},\n", field_name, field_name));
+            }
+            _ => out.push_str(&format!(
+                "        {}: __{}.expect(\"missing field {} ({})\"),\n",
+                field_name, field_name, field_name, name
+            )),
+        }
+    }
+    out.push_str("    }\n");
+    out.push_str("}\n\n");
+}
+
+/// How a single enum variant's data is laid out, mirroring the same
+/// `singleton`/wrapper-message split `emit.rs` uses for the schema side:
+/// a variant with no data is an empty payload, one with a single field
+/// rides directly on the case's own wire slot (no submessage framing),
+/// and one with several named fields gets its own length-delimited
+/// submessage with its own 1-based field numbering.
+enum VariantShape<'a> {
+    Empty,
+    Singleton(&'a TypeRef, Option<&'a str>),
+    Multi(&'a Vec<(String, TypeRef, Option<u32>)>),
+}
+
+fn variant_shape(fields: &Fields) -> VariantShape<'_> {
+    match fields {
+        Fields::Tuple(f) if f.is_empty() => VariantShape::Empty,
+        Fields::Tuple(f) if f.len() == 1 => VariantShape::Singleton(&f[0].0, None),
+        Fields::Tuple(_) => panic!("multi-field tuple variants are not supported"),
+        Fields::Struct(f) if f.is_empty() => VariantShape::Empty,
+        Fields::Struct(f) if f.len() == 1 => VariantShape::Singleton(&f[0].1, Some(&f[0].0)),
+        Fields::Struct(f) => VariantShape::Multi(f),
+    }
+}
+
+/// Whether a `Singleton` case's payload can occupy more than one wire entry
+/// under the same tag (any `repeated` field other than `Vec<u8>` bytes, or a
+/// `map`'s entries) and so
+/// needs an accumulator declared ahead of the decode loop, rather than
+/// being fully decoded from its first occurrence.
+fn singleton_needs_accumulator(ty: &TypeRef) -> bool {
+    match ty {
+        TypeRef::Generic(generic, args) => match generic.as_str() {
+            "Vec" => !matches!(&args[0], TypeRef::Normal(n) if n == "u8"),
+            "HashMap" => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn emit_enum_codec(ir: &IrModule, name: &str, item: &EnumTy, out: &mut String) {
+    let snake = to_underscore_case(name);
+
+    if item.variants.iter().all(|(_, f, _, _)| is_fieldless(f)) {
+        out.push_str(&format!("pub fn encode_{}(v: &{}, buf: &mut Vec<u8>) {{\n", snake, name));
+        let prim = Prim::EnumDiscriminant(name.to_string(), item.variants.iter().map(|(n, _, _, _)| n.clone()).collect());
+        write_value(&prim, "v", "buf", out);
+        out.push_str("}\n\n");
+
+        out.push_str(&format!("pub fn decode_{}(buf: &[u8]) -> {} {{\n", snake, name));
+        out.push_str("    let mut pos = 0usize;\n");
+        out.push_str(&format!("    {}\n", read_value(&prim, "buf", "pos")));
+        out.push_str("}\n\n");
+        return;
+    }
+
+    let numbers = assign_numbers(&item.variants.iter().map(|(_, _, _, id)| *id).collect::<Vec<_>>(), &item.reserved);
+
+    out.push_str(&format!("pub fn encode_{}(v: &{}, buf: &mut Vec<u8>) {{\n", snake, name));
+    out.push_str("    match v {\n");
+    for ((variant_name, fields, _, _), number) in item.variants.iter().zip(&numbers) {
+        let id = *number;
+        match variant_shape(fields) {
+            VariantShape::Empty => {
+                out.push_str(&format!("        {}::{} => {{\n", name, variant_name));
+                out.push_str(&format!("            write_tag(buf, {}, {});\n", id, WIRE_LEN));
+                out.push_str("            write_varint(buf, 0);\n");
+                out.push_str("        }\n");
+            }
+            VariantShape::Singleton(ty, None) => {
+                out.push_str(&format!("        {}::{}(__x) => {{\n", name, variant_name));
+                emit_field_encode_indented(ir, "*__x", ty, id, "buf", out, "        ");
+                out.push_str("        }\n");
+            }
+            VariantShape::Singleton(ty, Some(field_name)) => {
+                out.push_str(&format!("        {}::{} {{ {} }} => {{\n", name, variant_name, field_name));
+                emit_field_encode_indented(ir, &format!("*{}", field_name), ty, id, "buf", out, "        ");
+                out.push_str("        }\n");
+            }
+            VariantShape::Multi(fields) => {
+                let binds = fields.iter().map(|(n, _, _)| n.as_str()).collect::<Vec<_>>().join(", ");
+                out.push_str(&format!("        {}::{} {{ {} }} => {{\n", name, variant_name, binds));
+                out.push_str("            let mut __payload = Vec::new();\n");
+                out.push_str("            let __payload = &mut __payload;\n");
+                let sub_numbers = assign_numbers(&fields.iter().map(|(_, _, id)| *id).collect::<Vec<_>>(), &[]);
+                for ((field_name, ty, _), number) in fields.iter().zip(&sub_numbers) {
+                    emit_field_encode_indented(ir, &format!("*{}", field_name), ty, *number, "__payload", out, "        ");
+                }
+                out.push_str(&format!("            write_tag(buf, {}, {});\n", id, WIRE_LEN));
+                out.push_str("            write_varint(buf, __payload.len() as u64);\n");
+                out.push_str("            buf.extend_from_slice(&__payload);\n");
+                out.push_str("        }\n");
+            }
+        }
+    }
+    out.push_str("    }\n");
+    out.push_str("}\n\n");
+
+    // A `Singleton` whose payload is a `repeated` field (other than
+    // `Vec<u8>` bytes) or a `map` can be
+    // spread across more than one wire entry under the same tag, so those
+    // (and only those) need an accumulator declared ahead of the loop and
+    // are decoded after it ends instead of on their first occurrence.
+    let accumulated: Vec<(&str, u32, &TypeRef, Option<&str>)> = item.variants.iter().zip(&numbers)
+        .filter_map(|((variant_name, fields, _, _), number)| match variant_shape(fields) {
+            VariantShape::Singleton(ty, field_name) if singleton_needs_accumulator(ty) => {
+                Some((variant_name.as_str(), *number, ty, field_name))
+            }
+            _ => None,
+        })
+        .collect();
+
+    out.push_str(&format!("pub fn decode_{}(buf: &[u8]) -> {} {{\n", snake, name));
+    out.push_str("    let mut pos = 0usize;\n");
+    for (variant_name, _, ty, _) in &accumulated {
+        emit_field_decl(ty, &to_underscore_case(variant_name), out);
+    }
+    if !accumulated.is_empty() {
+        out.push_str("    let mut __case: Option<u32> = None;\n");
+    }
+    out.push_str("    while pos < buf.len() {\n");
+    out.push_str("        let (field_number, wire_type) = read_tag(buf, &mut pos);\n");
+    out.push_str("        match field_number {\n");
+    for ((variant_name, fields, _, _), number) in item.variants.iter().zip(&numbers) {
+        let id = *number;
+        match variant_shape(fields) {
+            VariantShape::Empty => {
+                out.push_str(&format!("            {} => {{\n", id));
+                out.push_str("                let len = read_varint(buf, &mut pos) as usize;\n");
+                out.push_str("                pos += len;\n");
+                out.push_str(&format!("                return {}::{};\n", name, variant_name));
+                out.push_str("            }\n");
+            }
+            VariantShape::Singleton(ty, field_name) if singleton_needs_accumulator(ty) => {
+                out.push_str(&format!("            {} => {{\n", id));
+                emit_field_decode_body(ir, &to_underscore_case(variant_name), ty, out);
+                out.push_str(&format!("                __case = Some({});\n", id));
+                out.push_str("            }\n");
+                let _ = field_name;
+            }
+            VariantShape::Singleton(ty, field_name) => {
+                emit_singleton_decode_return(ir, name, variant_name, field_name, ty, id, out);
+            }
+            VariantShape::Multi(fields) => {
+                out.push_str(&format!("            {} => {{\n", id));
+                out.push_str("                let len = read_varint(buf, &mut pos) as usize;\n");
+                out.push_str("                let entry = &buf[pos..pos + len];\n");
+                out.push_str("                pos += len;\n");
+                out.push_str("                let mut epos = 0usize;\n");
+                for (field_name, _, _) in fields {
+                    out.push_str(&format!("                let mut __{}_ = None;\n", field_name));
+                }
+                out.push_str("                while epos < entry.len() {\n");
+                out.push_str("                    let (efield, _ewire) = read_tag(entry, &mut epos);\n");
+                out.push_str("                    match efield {\n");
+                let sub_numbers = assign_numbers(&fields.iter().map(|(_, _, id)| *id).collect::<Vec<_>>(), &[]);
+                for ((field_name, ty, _), number) in fields.iter().zip(&sub_numbers) {
+                    let prim = classify(ir, &rust_type_name(ty));
+                    out.push_str(&format!(
+                        "                        {} => __{}_ = Some({}),\n",
+                        number,
+                        field_name,
+                        read_value(&prim, "entry", "epos")
+                    ));
+                }
+                out.push_str("                        _ => {}\n");
+                out.push_str("                    }\n");
+                out.push_str("                }\n");
+                out.push_str(&format!("                return {}::{} {{\n", name, variant_name));
+                for (field_name, _, _) in fields {
+                    out.push_str(&format!(
+                        "                    {}: __{}_.expect(\"missing field {} ({}{})\"),\n",
+                        field_name, field_name, field_name, name, variant_name
+                    ));
+                }
+                out.push_str("                };\n");
+                out.push_str("            }\n");
+            }
+        }
+    }
+    out.push_str("            _ => skip_field(buf, &mut pos, wire_type),\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    if accumulated.is_empty() {
+        out.push_str(&format!("    panic!(\"no {} case found in buffer\");\n", name));
+    } else {
+        out.push_str("    match __case.expect(\"no case found in buffer\") {\n");
+        for (variant_name, number, _, field_name) in &accumulated {
+            match field_name {
+                None => out.push_str(&format!("        {} => {}::{}(__{}),\n", number, name, variant_name, to_underscore_case(variant_name))),
+                Some(field_name) => out.push_str(&format!(
+                    "        {} => {}::{} {{ {}: __{} }},\n",
+                    number, name, variant_name, field_name, to_underscore_case(variant_name)
+                )),
+            }
+        }
+        out.push_str(&format!("        other => panic!(\"unknown {} case {{}}\", other),\n", name));
+        out.push_str("    }\n");
+    }
+    out.push_str("}\n\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn ir_from_rust(src: &str) -> IrModule {
+        let tree: syn::File = syn::parse_str(src).unwrap();
+        let mut ir = crate::rust_load::load_proto(&tree);
+        crate::ir::resolve_generics(&mut ir);
+        ir
+    }
+
+    fn compile_and_run(tag: &str, src: &str, harness: &str) {
+        let dir = std::env::temp_dir().join(format!("rproto_wire_codegen_test_{}", tag));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src_path = dir.join("main.rs");
+        let bin_path = dir.join("main");
+        std::fs::File::create(&src_path).unwrap().write_all(format!("{}\n{}", src, harness).as_bytes()).unwrap();
+
+        let status = std::process::Command::new("rustc")
+            .arg("--edition").arg("2021")
+            .arg(&src_path)
+            .arg("-o").arg(&bin_path)
+            .status()
+            .expect("failed to invoke rustc");
+        assert!(status.success(), "generated wire codec failed to compile");
+
+        let status = std::process::Command::new(&bin_path).status().expect("failed to run compiled binary");
+        assert!(status.success(), "encode/decode round trip failed at runtime");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn struct_roundtrip_compiles_and_matches() {
+        let ir = ir_from_rust("struct Widget { #[proto(id = 5)] a: u32, b: u64, name: String }");
+        let generated = emit_wire_codec(&ir);
+        let type_def = "#[derive(Debug, PartialEq)]\nstruct Widget { a: u32, b: u64, name: String }\n";
+        let harness = r#"
+fn main() {
+    let v = Widget { a: 7, b: 400000, name: "hi".to_string() };
+    let mut buf = Vec::new();
+    encode_widget(&v, &mut buf);
+    let back = decode_widget(&buf);
+    assert_eq!(v, back);
+}
+"#;
+        compile_and_run("struct", &format!("{}\n{}", type_def, generated), harness);
+    }
+
+    #[test]
+    fn generic_singleton_variant_roundtrip_compiles_and_matches() {
+        let src = "enum Msg { Nums(Vec<u32>), Name(String), Empty, Data(Vec<u8>), Note(Option<u32>) }";
+        let ir = ir_from_rust(src);
+        let generated = emit_wire_codec(&ir);
+        let type_def = format!("#[derive(Debug, PartialEq)]\n{}\n", src);
+        let harness = r#"
+fn main() {
+    for v in [
+        Msg::Nums(vec![1, 2, 3, 400000]),
+        Msg::Name("hello".to_string()),
+        Msg::Empty,
+        Msg::Data(vec![1, 2, 3, 255]),
+        Msg::Note(Some(42)),
+    ] {
+        let mut buf = Vec::new();
+        encode_msg(&v, &mut buf);
+        let back = decode_msg(&buf);
+        assert_eq!(v, back);
+    }
+}
+"#;
+        compile_and_run("generic_variant", &format!("{}\n{}", type_def, generated), harness);
+    }
+}
+
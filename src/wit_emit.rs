@@ -0,0 +1,181 @@
+//! Renders an `IrModule` as a WIT (WebAssembly Interface Types) `interface`
+//! body: structs become `record`, fieldless enums become `enum`, and
+//! data-carrying enums become `variant`.
+
+use crate::backend::{Backend, FieldSite};
+
+pub(crate) struct WitEmitter {
+    buf: String,
+    at_line_start: bool,
+    indent: usize,
+}
+
+impl WitEmitter {
+    pub(crate) fn new() -> WitEmitter {
+        WitEmitter {
+            buf: String::new(),
+            at_line_start: true,
+            indent: 0,
+        }
+    }
+
+    fn _output(&mut self, text: &str) {
+        self.buf.push_str(text);
+        print!("{}", text);
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.at_line_start {
+            for _ in 0..self.indent {
+                self._output("  ");
+            }
+        }
+        self.at_line_start = false;
+        self._output(text);
+    }
+
+    fn line(&mut self) {
+        self.text("\n");
+        self.at_line_start = true;
+    }
+
+    fn record_field(&mut self, name: &str, ty: &str) {
+        self.text(&to_kebab_case(name));
+        self.text(": ");
+        self.text(ty);
+        self.text(",");
+        self.line();
+    }
+
+    fn variant_case(&mut self, name: &str, ty: Option<&str>) {
+        self.text(&to_kebab_case(name));
+        if let Some(ty) = ty {
+            self.text("(");
+            self.text(ty);
+            self.text(")");
+        }
+        self.text(",");
+        self.line();
+    }
+}
+
+impl Backend for WitEmitter {
+    fn begin_message(&mut self, name: &str) {
+        self.text("record ");
+        self.text(&to_kebab_case(name));
+        self.text(" {");
+        self.indent += 1;
+        self.line();
+    }
+
+    fn begin_variant(&mut self, name: &str) {
+        self.text("variant ");
+        self.text(&to_kebab_case(name));
+        self.text(" {");
+        self.indent += 1;
+        self.line();
+    }
+
+    fn end(&mut self) {
+        self.indent -= 1;
+        self.text("}");
+        self.line();
+    }
+
+    fn variant_needs_message_wrapper(&self) -> bool {
+        false
+    }
+
+    fn scalar_type_name(&self, name: &str) -> Option<&'static str> {
+        match name {
+            "u8" => Some("u8"),
+            "u16" => Some("u16"),
+            "u32" => Some("u32"),
+            "u64" => Some("u64"),
+            "i8" => Some("s8"),
+            "i16" => Some("s16"),
+            "i32" => Some("s32"),
+            "i64" => Some("s64"),
+            "f32" => Some("f32"),
+            "f64" => Some("f64"),
+            "bool" => Some("bool"),
+            "String" => Some("string"),
+            _ => None,
+        }
+    }
+
+    fn scalar_field(&mut self, site: FieldSite, name: &str, ty: &str, _id: &mut usize) {
+        match site {
+            FieldSite::Message => self.record_field(name, ty),
+            FieldSite::Case => self.variant_case(name, Some(ty)),
+        }
+    }
+
+    fn message_field(&mut self, site: FieldSite, name: &str, ty: &str, _id: &mut usize) {
+        match site {
+            FieldSite::Message => self.record_field(name, ty),
+            FieldSite::Case => self.variant_case(name, Some(ty)),
+        }
+    }
+
+    fn repeated_field(&mut self, site: FieldSite, name: &str, ty: &str, _id: &mut usize) {
+        let list_ty = format!("list<{}>", ty);
+        match site {
+            FieldSite::Message => self.record_field(name, &list_ty),
+            FieldSite::Case => self.variant_case(name, Some(&list_ty)),
+        }
+    }
+
+    fn map_field(&mut self, site: FieldSite, name: &str, key_ty: &str, value_ty: &str, _id: &mut usize) {
+        let map_ty = format!("list<tuple<{}, {}>>", key_ty, value_ty);
+        match site {
+            FieldSite::Message => self.record_field(name, &map_ty),
+            FieldSite::Case => self.variant_case(name, Some(&map_ty)),
+        }
+    }
+
+    fn optional_field(&mut self, site: FieldSite, name: &str, ty: &str, _id: &mut usize) {
+        let opt_ty = format!("option<{}>", ty);
+        match site {
+            FieldSite::Message => self.record_field(name, &opt_ty),
+            FieldSite::Case => self.variant_case(name, Some(&opt_ty)),
+        }
+    }
+
+    fn supports_native_enum(&self) -> bool {
+        true
+    }
+
+    fn begin_enum(&mut self, name: &str) {
+        self.text("enum ");
+        self.text(&to_kebab_case(name));
+        self.text(" {");
+        self.indent += 1;
+        self.line();
+    }
+
+    fn enum_variant(&mut self, name: &str, _discriminant: usize) {
+        self.text(&to_kebab_case(name));
+        self.text(",");
+        self.line();
+    }
+}
+
+/// WIT identifiers are conventionally kebab-case; translate from the
+/// Rust-side `CamelCase`/`snake_case` names instead of emitting invalid WIT.
+fn to_kebab_case(name: &str) -> String {
+    let mut s = String::new();
+    for ch in name.chars() {
+        if ch == '_' {
+            s.push('-');
+        } else if ch.is_uppercase() {
+            if !s.is_empty() && !s.ends_with('-') {
+                s.push('-');
+            }
+            s.push_str(&ch.to_lowercase().to_string());
+        } else {
+            s.push(ch);
+        }
+    }
+    s
+}